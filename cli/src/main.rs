@@ -1,5 +1,10 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Write;
+use std::time::Duration;
+
+use forkstack::provider::storage_provider;
+use forkstack::storage::{PresignMethod, DEFAULT_PRESIGN_EXPIRY};
 
 #[derive(Parser)]
 #[command(name = "forks")]
@@ -24,6 +29,41 @@ enum Commands {
         /// Name of the fork to delete
         name: String,
     },
+    /// Reconcile Turso databases and storage, and garbage-collect orphaned forks
+    Prune {
+        /// Delete the orphans found without asking for confirmation
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Generate presigned URLs for a fork's storage
+    Url {
+        /// Name of the fork
+        name: String,
+        /// Only presign this object, relative to the fork's storage root
+        #[arg(long)]
+        key: Option<String>,
+        /// HTTP method to presign for
+        #[arg(long, value_enum, default_value_t = UrlMethod::Get)]
+        method: UrlMethod,
+        /// URL expiry, in seconds
+        #[arg(long, default_value_t = DEFAULT_PRESIGN_EXPIRY.as_secs())]
+        expires_in: u64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum UrlMethod {
+    Get,
+    Put,
+}
+
+impl From<UrlMethod> for PresignMethod {
+    fn from(method: UrlMethod) -> Self {
+        match method {
+            UrlMethod::Get => PresignMethod::Get,
+            UrlMethod::Put => PresignMethod::Put,
+        }
+    }
 }
 
 #[tokio::main]
@@ -36,6 +76,7 @@ async fn main() -> Result<()> {
             println!("Created fork: {}", fork.name);
             println!("Database: {}", fork.database_url);
             println!("Storage:  {}", fork.storage_url);
+            println!("Objects copied: {}", fork.objects_copied);
         }
         Commands::List => {
             let forks = forkstack::list_forks().await?;
@@ -52,6 +93,65 @@ async fn main() -> Result<()> {
             forkstack::delete_fork(&name).await?;
             println!("Deleted fork: {}", name);
         }
+        Commands::Prune { delete } => {
+            let report = forkstack::prune::scan().await?;
+
+            if report.is_empty() {
+                println!("No orphaned forks found.");
+                return Ok(());
+            }
+
+            if !report.storage_orphans.is_empty() {
+                println!("Storage with no matching database:");
+                for orphan in &report.storage_orphans {
+                    println!("  {} ({})", orphan.fork_name, orphan.bucket_name);
+                }
+            }
+
+            if !report.database_orphans.is_empty() {
+                println!("Databases with no matching storage:");
+                for orphan in &report.database_orphans {
+                    println!("  {}", orphan.fork_name);
+                }
+            }
+
+            let confirmed = delete || {
+                print!("Delete these orphans? [y/N] ");
+                std::io::stdout().flush()?;
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+            };
+
+            if confirmed {
+                forkstack::prune::delete_orphans(&report).await?;
+                println!("Orphans deleted.");
+            } else {
+                println!("Dry run only; re-run with --delete to remove these.");
+            }
+        }
+        Commands::Url {
+            name,
+            key,
+            method,
+            expires_in,
+        } => {
+            let config = forkstack::config::Config::load()?;
+            let expires_in = Duration::from_secs(expires_in);
+
+            for (bucket_name, storage_config) in config.storage_configs() {
+                let storage =
+                    storage_provider(storage_config, &storage_config.fork_prefix()).await?;
+                let urls = storage
+                    .presign_fork(&name, key.as_deref(), method.into(), expires_in)
+                    .await?;
+
+                println!("{}:", bucket_name);
+                for presigned in urls {
+                    println!("  {}  {}", presigned.key, presigned.url);
+                }
+            }
+        }
     }
 
     Ok(())
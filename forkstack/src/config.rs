@@ -29,6 +29,32 @@ pub struct StorageConfig {
     pub endpoint: Option<String>,
     pub region: Option<String>,
     pub prefix: Option<String>,
+    /// Where to source credentials for this bucket. Falls back to the AWS
+    /// default provider chain (env vars, profile, IMDS, ...) when unset.
+    #[serde(default)]
+    pub credentials: Option<CredentialsConfig>,
+    /// Max concurrent copy/delete requests in flight for this bucket.
+    /// Defaults to `storage::DEFAULT_CONCURRENCY` when unset.
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+/// Explicit credential source for a [`StorageConfig`], for machines that have
+/// several buckets authenticated against different profiles or sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum CredentialsConfig {
+    /// A named profile from `~/.aws/credentials` or `~/.aws/config`
+    Profile { name: String },
+    /// Environment variable names to read the access key and secret from
+    Env {
+        access_key_id_var: String,
+        secret_access_key_var: String,
+    },
+    /// EC2 instance metadata service (IMDS)
+    Imds,
+    /// AWS SSO, resolved through an (optionally named) profile
+    Sso { profile: Option<String> },
 }
 
 impl Config {
@@ -226,6 +252,159 @@ prefix = "dev-forks/"
         assert_eq!(uploads.fork_prefix(), "dev-forks/");
     }
 
+    #[test]
+    fn test_storage_concurrency_default_unset() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        assert_eq!(uploads.concurrency, None);
+    }
+
+    #[test]
+    fn test_storage_concurrency_custom() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+concurrency = 8
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        assert_eq!(uploads.concurrency, Some(8));
+    }
+
+    #[test]
+    fn test_storage_credentials_default_unset() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        assert!(uploads.credentials.is_none());
+    }
+
+    #[test]
+    fn test_storage_credentials_profile() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+
+[storage.uploads.credentials]
+source = "profile"
+name = "uploads-profile"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        match &uploads.credentials {
+            Some(CredentialsConfig::Profile { name }) => assert_eq!(name, "uploads-profile"),
+            other => panic!("expected profile credentials, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_storage_credentials_env() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+
+[storage.uploads.credentials]
+source = "env"
+access_key_id_var = "UPLOADS_ACCESS_KEY_ID"
+secret_access_key_var = "UPLOADS_SECRET_ACCESS_KEY"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        match &uploads.credentials {
+            Some(CredentialsConfig::Env {
+                access_key_id_var,
+                secret_access_key_var,
+            }) => {
+                assert_eq!(access_key_id_var, "UPLOADS_ACCESS_KEY_ID");
+                assert_eq!(secret_access_key_var, "UPLOADS_SECRET_ACCESS_KEY");
+            }
+            other => panic!("expected env credentials, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_storage_credentials_imds() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+
+[storage.uploads.credentials]
+source = "imds"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        assert!(matches!(uploads.credentials, Some(CredentialsConfig::Imds)));
+    }
+
+    #[test]
+    fn test_storage_credentials_sso() {
+        let config_str = r#"
+[database]
+provider = "turso"
+organization = "my-org"
+production = "my-db"
+
+[storage.uploads]
+provider = "tigris"
+bucket = "my-bucket"
+
+[storage.uploads.credentials]
+source = "sso"
+profile = "uploads-sso"
+"#;
+        let config: Config = toml::from_str(config_str).unwrap();
+        let uploads = config.storage.get("uploads").unwrap();
+        match &uploads.credentials {
+            Some(CredentialsConfig::Sso { profile }) => {
+                assert_eq!(profile.as_deref(), Some("uploads-sso"))
+            }
+            other => panic!("expected sso credentials, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_load_from_path() {
         let mut file = NamedTempFile::new().unwrap();
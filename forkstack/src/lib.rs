@@ -4,16 +4,18 @@
 //! isolated development environments.
 
 pub mod config;
+pub mod provider;
+pub mod prune;
 pub mod storage;
 pub mod turso;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use config::Config;
-use storage::StorageClient;
-use turso::TursoClient;
+use provider::{database_provider, storage_provider, StorageProvider};
 
 /// A fork represents an isolated development environment
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +24,21 @@ pub struct Fork {
     pub database_url: String,
     pub storage_url: String,
     pub created_at: u64,
+    /// Total objects copied into storage across all buckets. Only known at
+    /// creation time; reconstructed forks from `list_forks` report 0.
+    pub objects_copied: usize,
+}
+
+/// Manifest written to `{prefix}{fork_name}/.forkstack.json` in every configured
+/// bucket during `create_fork`, so `list_forks` can describe a fork's storage
+/// without re-crawling every bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ForkManifest {
+    name: String,
+    created_at: u64,
+    /// Bucket name (as configured in `.forkstack.toml`) to that bucket's fork URL.
+    storage_urls: HashMap<String, String>,
+    production_database: String,
 }
 
 impl Fork {
@@ -54,27 +71,47 @@ pub async fn create_fork(name: Option<String>) -> Result<Fork> {
         .unwrap()
         .as_secs();
 
-    // Create Turso database fork
-    let turso = TursoClient::new(&config.database.organization)?;
-    let db_info = turso
+    // Create the database fork
+    let database = database_provider(&config.database)?;
+    let db_handle = database
         .create_fork(&fork_name, &config.database.production, &config.database_group())
         .await?;
 
-    let database_url = format!("libsql://{}", db_info.hostname);
+    let database_url = format!("libsql://{}", db_handle.hostname);
 
-    // Copy storage for each configured bucket
-    let mut storage_urls = Vec::new();
+    // Copy storage for each configured bucket, keeping the client around so we
+    // can write the fork manifest to the same buckets afterwards.
+    let mut storage_clients = Vec::new();
     for (bucket_name, storage_config) in config.storage_configs() {
-        let storage = StorageClient::new(storage_config, &storage_config.fork_prefix()).await?;
-        let url = storage.copy_to_fork("", &fork_name).await?;
-        storage_urls.push(format!("{}: {}", bucket_name, url));
+        let storage = storage_provider(storage_config, &storage_config.fork_prefix()).await?;
+        let summary = storage.copy_to_fork("", &fork_name).await?;
+        storage_clients.push((bucket_name.clone(), storage, summary));
+    }
+
+    let storage_urls: HashMap<String, String> = storage_clients
+        .iter()
+        .map(|(bucket_name, _, summary)| (bucket_name.clone(), summary.url.clone()))
+        .collect();
+    let objects_copied: usize = storage_clients.iter().map(|(_, _, summary)| summary.copied).sum();
+
+    let manifest = ForkManifest {
+        name: fork_name.clone(),
+        created_at: now,
+        storage_urls: storage_urls.clone(),
+        production_database: config.database.production.clone(),
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+
+    for (_, storage, _) in &storage_clients {
+        storage.write_manifest(&fork_name, &manifest_bytes).await?;
     }
 
     let fork = Fork {
         name: fork_name,
         database_url,
-        storage_url: storage_urls.join("\n"),
+        storage_url: format_storage_urls(&storage_urls),
         created_at: now,
+        objects_copied,
     };
 
     Ok(fork)
@@ -84,36 +121,81 @@ pub async fn create_fork(name: Option<String>) -> Result<Fork> {
 pub async fn list_forks() -> Result<Vec<Fork>> {
     let config = Config::load()?;
 
-    // Get databases from Turso that look like forks
-    let turso = TursoClient::new(&config.database.organization)?;
-    let databases = turso.list_databases().await?;
+    // Get databases that look like forks
+    let database = database_provider(&config.database)?;
+    let databases = database.list().await?;
 
-    // Filter to databases that aren't the production one
-    let forks: Vec<Fork> = databases
-        .into_iter()
-        .filter(|db| db.name != config.database.production)
-        .map(|db| Fork {
+    // Build each bucket's storage client once, up front, rather than
+    // re-resolving credentials for every fork we read a manifest for.
+    let mut storage_clients = Vec::new();
+    for (_, storage_config) in config.storage_configs() {
+        storage_clients.push(storage_provider(storage_config, &storage_config.fork_prefix()).await?);
+    }
+
+    let mut forks = Vec::new();
+    for db in databases {
+        // Filter to databases that aren't the production one
+        if db.name == config.database.production {
+            continue;
+        }
+
+        let manifest = read_fork_manifest(&db.name, &storage_clients).await?;
+
+        forks.push(Fork {
             name: db.name.clone(),
             database_url: format!("libsql://{}", db.hostname),
-            storage_url: String::new(), // Could query S3 but adds latency
-            created_at: 0,              // Turso API doesn't return this easily
-        })
-        .collect();
+            storage_url: manifest
+                .as_ref()
+                .map(|m| format_storage_urls(&m.storage_urls))
+                .unwrap_or_default(),
+            created_at: manifest.map(|m| m.created_at).unwrap_or(0),
+            // Only known at creation time; a listed fork was copied long ago.
+            objects_copied: 0,
+        });
+    }
 
     Ok(forks)
 }
 
+/// Read a fork's manifest from whichever configured bucket has it, since every
+/// bucket gets an identical copy at `{prefix}{fork_name}/.forkstack.json`.
+async fn read_fork_manifest(
+    fork_name: &str,
+    storage_clients: &[Box<dyn StorageProvider>],
+) -> Result<Option<ForkManifest>> {
+    for storage in storage_clients {
+        if let Some(bytes) = storage.read_manifest(fork_name).await? {
+            return Ok(Some(serde_json::from_slice(&bytes)?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Render a fork's per-bucket storage URLs the way the CLI displays them,
+/// sorted by bucket name for stable output.
+fn format_storage_urls(storage_urls: &HashMap<String, String>) -> String {
+    let mut entries: Vec<_> = storage_urls.iter().collect();
+    entries.sort_by_key(|(bucket_name, _)| bucket_name.as_str());
+
+    entries
+        .into_iter()
+        .map(|(bucket_name, url)| format!("{}: {}", bucket_name, url))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Delete a fork
 pub async fn delete_fork(name: &str) -> Result<()> {
     let config = Config::load()?;
 
-    // Delete Turso database
-    let turso = TursoClient::new(&config.database.organization)?;
-    turso.delete_database(name).await?;
+    // Delete the database fork
+    let database = database_provider(&config.database)?;
+    database.delete(name).await?;
 
     // Delete storage for each configured bucket
     for (_bucket_name, storage_config) in config.storage_configs() {
-        let storage = StorageClient::new(storage_config, &storage_config.fork_prefix()).await?;
+        let storage = storage_provider(storage_config, &storage_config.fork_prefix()).await?;
         storage.delete_fork(name).await?;
     }
 
@@ -0,0 +1,131 @@
+//! Pluggable database and storage backends, selected by the `provider`
+//! string in each `.forkstack.toml` section.
+//!
+//! Only "turso" and the S3-compatible family ("s3", "tigris", ...) are
+//! implemented today, but orchestration in `lib.rs` goes through these
+//! traits so a non-Turso libsql host or a new storage backend only needs a
+//! new impl here, not changes anywhere else.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+
+use crate::config::{DatabaseConfig, StorageConfig};
+use crate::storage::{CopySummary, PresignMethod, PresignedUrl, StorageClient};
+use crate::turso::TursoClient;
+
+/// A forked database, as reported by a [`DatabaseProvider`]
+#[derive(Debug, Clone)]
+pub struct DatabaseHandle {
+    pub name: String,
+    pub hostname: String,
+}
+
+/// A database backend capable of forking, listing, and deleting databases
+#[async_trait]
+pub trait DatabaseProvider: Send + Sync {
+    async fn create_fork(&self, name: &str, from_db: &str, group: &str) -> Result<DatabaseHandle>;
+    async fn list(&self) -> Result<Vec<DatabaseHandle>>;
+    async fn delete(&self, name: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl DatabaseProvider for TursoClient {
+    async fn create_fork(&self, name: &str, from_db: &str, group: &str) -> Result<DatabaseHandle> {
+        let info = TursoClient::create_fork(self, name, from_db, group).await?;
+        Ok(DatabaseHandle {
+            name: info.name,
+            hostname: info.hostname,
+        })
+    }
+
+    async fn list(&self) -> Result<Vec<DatabaseHandle>> {
+        let databases = TursoClient::list_databases(self).await?;
+        Ok(databases
+            .into_iter()
+            .map(|db| DatabaseHandle {
+                name: db.name,
+                hostname: db.hostname,
+            })
+            .collect())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        TursoClient::delete_database(self, name).await
+    }
+}
+
+/// Construct the [`DatabaseProvider`] named by `config.provider`
+pub fn database_provider(config: &DatabaseConfig) -> Result<Box<dyn DatabaseProvider>> {
+    match config.provider.as_str() {
+        "turso" => Ok(Box::new(TursoClient::new(&config.organization)?)),
+        other => anyhow::bail!("Unknown database provider: {}", other),
+    }
+}
+
+/// A storage backend capable of forking and reconciling a bucket's fork
+/// prefixes, plus the fork manifest that records a fork's metadata.
+#[async_trait]
+pub trait StorageProvider: Send + Sync {
+    async fn copy_to_fork(&self, source_prefix: &str, fork_name: &str) -> Result<CopySummary>;
+    async fn delete_fork(&self, fork_name: &str) -> Result<usize>;
+    async fn list_forks(&self) -> Result<Vec<String>>;
+    async fn write_manifest(&self, fork_name: &str, contents: &[u8]) -> Result<()>;
+    async fn read_manifest(&self, fork_name: &str) -> Result<Option<Vec<u8>>>;
+    async fn presign_fork(
+        &self,
+        fork_name: &str,
+        object_key: Option<&str>,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<Vec<PresignedUrl>>;
+}
+
+#[async_trait]
+impl StorageProvider for StorageClient {
+    async fn copy_to_fork(&self, source_prefix: &str, fork_name: &str) -> Result<CopySummary> {
+        StorageClient::copy_to_fork(self, source_prefix, fork_name).await
+    }
+
+    async fn delete_fork(&self, fork_name: &str) -> Result<usize> {
+        StorageClient::delete_fork(self, fork_name).await
+    }
+
+    async fn list_forks(&self) -> Result<Vec<String>> {
+        StorageClient::list_forks(self).await
+    }
+
+    async fn write_manifest(&self, fork_name: &str, contents: &[u8]) -> Result<()> {
+        StorageClient::write_manifest(self, fork_name, contents).await
+    }
+
+    async fn read_manifest(&self, fork_name: &str) -> Result<Option<Vec<u8>>> {
+        StorageClient::read_manifest(self, fork_name).await
+    }
+
+    async fn presign_fork(
+        &self,
+        fork_name: &str,
+        object_key: Option<&str>,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<Vec<PresignedUrl>> {
+        StorageClient::presign_fork(self, fork_name, object_key, method, expires_in).await
+    }
+}
+
+/// Providers that all speak the S3 API (native or compatible) through `aws-sdk-s3`,
+/// just against different endpoints.
+const S3_COMPATIBLE_PROVIDERS: &[&str] = &["s3", "tigris", "r2", "minio", "garage"];
+
+/// Construct the [`StorageProvider`] named by `config.provider`
+pub async fn storage_provider(
+    config: &StorageConfig,
+    prefix: &str,
+) -> Result<Box<dyn StorageProvider>> {
+    if S3_COMPATIBLE_PROVIDERS.contains(&config.provider.as_str()) {
+        Ok(Box::new(StorageClient::new(config, prefix).await?))
+    } else {
+        anyhow::bail!("Unknown storage provider: {}", config.provider)
+    }
+}
@@ -0,0 +1,185 @@
+//! Garbage-collection for orphaned forks.
+//!
+//! Cross-checks Turso databases against storage fork prefixes so a crashed
+//! `create`/`delete` can't leak resources forever. Fork manifests are not
+//! read; a prefix counts as storage for a fork whether or not it holds one.
+
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::Config;
+use crate::provider::{database_provider, storage_provider};
+
+/// A fork prefix found in a bucket with no corresponding Turso database.
+#[derive(Debug, Clone)]
+pub struct StorageOrphan {
+    pub bucket_name: String,
+    pub fork_name: String,
+}
+
+/// A Turso database with no storage prefix left in any configured bucket.
+#[derive(Debug, Clone)]
+pub struct DatabaseOrphan {
+    pub fork_name: String,
+}
+
+/// Result of cross-checking Turso databases against storage fork prefixes.
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    pub storage_orphans: Vec<StorageOrphan>,
+    pub database_orphans: Vec<DatabaseOrphan>,
+}
+
+impl PruneReport {
+    pub fn is_empty(&self) -> bool {
+        self.storage_orphans.is_empty() && self.database_orphans.is_empty()
+    }
+}
+
+/// Build the set of "live" fork names from Turso, then stream each bucket's
+/// fork prefixes and flag anything not in that set. Databases left with no
+/// storage anywhere are reported the other way around.
+pub async fn scan() -> Result<PruneReport> {
+    let config = Config::load()?;
+
+    let database = database_provider(&config.database)?;
+    let live_forks: HashSet<String> = database
+        .list()
+        .await?
+        .into_iter()
+        .map(|db| db.name)
+        .filter(|name| *name != config.database.production)
+        .collect();
+
+    let mut bucket_forks = Vec::new();
+    for (bucket_name, storage_config) in config.storage_configs() {
+        let storage = storage_provider(storage_config, &storage_config.fork_prefix()).await?;
+        bucket_forks.push((bucket_name.clone(), storage.list_forks().await?));
+    }
+
+    Ok(reconcile(live_forks, &bucket_forks))
+}
+
+/// Pure set-reconciliation between Turso's live forks and each bucket's fork
+/// prefixes, split out from [`scan`] so it can be tested without a network.
+fn reconcile(live_forks: HashSet<String>, bucket_forks: &[(String, Vec<String>)]) -> PruneReport {
+    let mut storage_orphans = Vec::new();
+    let mut forks_with_storage: HashSet<String> = HashSet::new();
+
+    for (bucket_name, fork_names) in bucket_forks {
+        for fork_name in fork_names {
+            forks_with_storage.insert(fork_name.clone());
+            if !live_forks.contains(fork_name) {
+                storage_orphans.push(StorageOrphan {
+                    bucket_name: bucket_name.clone(),
+                    fork_name: fork_name.clone(),
+                });
+            }
+        }
+    }
+
+    let database_orphans = live_forks
+        .into_iter()
+        .filter(|fork_name| !forks_with_storage.contains(fork_name))
+        .map(|fork_name| DatabaseOrphan { fork_name })
+        .collect();
+
+    PruneReport {
+        storage_orphans,
+        database_orphans,
+    }
+}
+
+/// Delete every orphan identified by a prior [`scan`].
+pub async fn delete_orphans(report: &PruneReport) -> Result<()> {
+    let config = Config::load()?;
+
+    // Group by bucket so each bucket's client (and credential resolution) is
+    // built once, instead of once per orphan fork in that bucket.
+    let mut orphans_by_bucket: HashMap<&str, Vec<&str>> = HashMap::new();
+    for orphan in &report.storage_orphans {
+        orphans_by_bucket
+            .entry(orphan.bucket_name.as_str())
+            .or_default()
+            .push(orphan.fork_name.as_str());
+    }
+
+    for (bucket_name, fork_names) in orphans_by_bucket {
+        let storage_config = config.storage.get(bucket_name).with_context(|| {
+            format!(
+                "Bucket {} no longer in config; re-run `forks prune` to rescan",
+                bucket_name
+            )
+        })?;
+        let storage = storage_provider(storage_config, &storage_config.fork_prefix()).await?;
+        for fork_name in fork_names {
+            storage.delete_fork(fork_name).await?;
+        }
+    }
+
+    if !report.database_orphans.is_empty() {
+        let database = database_provider(&config.database)?;
+        for orphan in &report.database_orphans {
+            database.delete(&orphan.fork_name).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forks(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_reconcile_no_orphans() {
+        let live = HashSet::from(["fork-a".to_string(), "fork-b".to_string()]);
+        let bucket_forks = vec![("uploads".to_string(), forks(&["fork-a", "fork-b"]))];
+
+        let report = reconcile(live, &bucket_forks);
+
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_storage_orphan() {
+        let live = HashSet::from(["fork-a".to_string()]);
+        let bucket_forks = vec![("uploads".to_string(), forks(&["fork-a", "fork-b"]))];
+
+        let report = reconcile(live, &bucket_forks);
+
+        assert_eq!(report.storage_orphans.len(), 1);
+        assert_eq!(report.storage_orphans[0].bucket_name, "uploads");
+        assert_eq!(report.storage_orphans[0].fork_name, "fork-b");
+        assert!(report.database_orphans.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_database_orphan() {
+        let live = HashSet::from(["fork-a".to_string(), "fork-b".to_string()]);
+        let bucket_forks = vec![("uploads".to_string(), forks(&["fork-a"]))];
+
+        let report = reconcile(live, &bucket_forks);
+
+        assert!(report.storage_orphans.is_empty());
+        assert_eq!(report.database_orphans.len(), 1);
+        assert_eq!(report.database_orphans[0].fork_name, "fork-b");
+    }
+
+    #[test]
+    fn test_reconcile_fork_present_in_any_bucket_is_not_a_database_orphan() {
+        let live = HashSet::from(["fork-a".to_string()]);
+        let bucket_forks = vec![
+            ("uploads".to_string(), forks(&[])),
+            ("assets".to_string(), forks(&["fork-a"])),
+        ];
+
+        let report = reconcile(live, &bucket_forks);
+
+        assert!(report.is_empty());
+    }
+}
@@ -1,14 +1,121 @@
 //! S3-compatible storage client for bucket operations
 
 use anyhow::{Context, Result};
+use aws_config::imds::credentials::ImdsCredentialsProvider;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_credential_types::provider::SharedCredentialsProvider;
+use aws_credential_types::Credentials;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, Object};
 use aws_sdk_s3::Client;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::time::Duration;
 
-use crate::config::StorageConfig;
+use crate::config::{CredentialsConfig, StorageConfig};
+
+/// Default number of `copy_object`/`delete_object` requests kept in flight at once.
+const DEFAULT_CONCURRENCY: usize = 32;
+
+/// Default lifetime of a presigned URL from [`StorageClient::presign_fork`].
+pub const DEFAULT_PRESIGN_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// `CopyObject` rejects sources over this size; anything bigger must go through
+/// a multipart copy (`upload_part_copy` per byte range) instead.
+const MULTIPART_THRESHOLD_BYTES: i64 = 5 * 1024 * 1024 * 1024;
+
+/// Size of each part in a multipart copy, unless `MAX_MULTIPART_PARTS` forces it larger.
+const MULTIPART_PART_SIZE_BYTES: i64 = 256 * 1024 * 1024;
+
+/// S3 allows at most 10,000 parts per multipart upload.
+const MAX_MULTIPART_PARTS: i64 = 10_000;
+
+/// Characters `x-amz-copy-source` needs percent-encoded; unreserved characters
+/// and the path separator are left as-is so the header stays a valid path.
+const COPY_SOURCE_KEY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+/// Build the `x-amz-copy-source` value for `bucket`/`key`, percent-encoding the
+/// key so reserved/special characters (spaces, `+`, parens, unicode) survive.
+fn encode_copy_source(bucket: &str, key: &str) -> String {
+    format!(
+        "{}/{}",
+        bucket,
+        utf8_percent_encode(key, COPY_SOURCE_KEY_ENCODE_SET)
+    )
+}
+
+/// Outcome of copying a prefix into a fork
+pub struct CopySummary {
+    pub url: String,
+    pub copied: usize,
+}
+
+/// HTTP method to presign a URL for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresignMethod {
+    Get,
+    Put,
+}
+
+/// A presigned URL for one object under a fork's storage prefix
+#[derive(Debug, Clone)]
+pub struct PresignedUrl {
+    pub key: String,
+    pub url: String,
+}
+
+/// Build an explicit credentials provider for one bucket's configured source,
+/// instead of relying on the ambient default provider chain.
+fn build_credentials_provider(config: &CredentialsConfig) -> Result<SharedCredentialsProvider> {
+    let provider = match config {
+        CredentialsConfig::Profile { name } => SharedCredentialsProvider::new(
+            ProfileFileCredentialsProvider::builder()
+                .profile_name(name)
+                .build(),
+        ),
+        CredentialsConfig::Env {
+            access_key_id_var,
+            secret_access_key_var,
+        } => {
+            let access_key_id = std::env::var(access_key_id_var)
+                .with_context(|| format!("{} environment variable not set", access_key_id_var))?;
+            let secret_access_key = std::env::var(secret_access_key_var).with_context(|| {
+                format!("{} environment variable not set", secret_access_key_var)
+            })?;
+            SharedCredentialsProvider::new(Credentials::new(
+                access_key_id,
+                secret_access_key,
+                None,
+                None,
+                "forkstack-env",
+            ))
+        }
+        CredentialsConfig::Imds => {
+            SharedCredentialsProvider::new(ImdsCredentialsProvider::builder().build())
+        }
+        CredentialsConfig::Sso { profile } => {
+            let mut builder = ProfileFileCredentialsProvider::builder();
+            if let Some(profile) = profile {
+                builder = builder.profile_name(profile);
+            }
+            SharedCredentialsProvider::new(builder.build())
+        }
+    };
+
+    Ok(provider)
+}
 
 pub struct StorageClient {
     client: Client,
     bucket: String,
     prefix: String,
+    concurrency: usize,
 }
 
 impl StorageClient {
@@ -16,6 +123,12 @@ impl StorageClient {
     pub async fn new(config: &StorageConfig, prefix: &str) -> Result<Self> {
         let mut aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest());
 
+        // Use an explicit credential source when configured, instead of
+        // relying on the ambient default provider chain for every bucket.
+        if let Some(credentials) = &config.credentials {
+            aws_config = aws_config.credentials_provider(build_credentials_provider(credentials)?);
+        }
+
         // Set custom endpoint for Tigris or other S3-compatible services
         if let Some(endpoint) = &config.endpoint {
             aws_config = aws_config.endpoint_url(endpoint);
@@ -33,108 +146,384 @@ impl StorageClient {
             client,
             bucket: config.bucket.clone(),
             prefix: prefix.to_string(),
+            concurrency: config.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
         })
     }
 
-    /// Copy all objects from source prefix to fork prefix
-    pub async fn copy_to_fork(&self, source_prefix: &str, fork_name: &str) -> Result<String> {
-        let fork_prefix = format!("{}{}/", self.prefix, fork_name);
-
-        // List all objects under source prefix
-        let mut continuation_token: Option<String> = None;
+    /// Stream pages of `list_objects_v2` results under `prefix`, decoupling pagination
+    /// from whatever the caller does with each page's keys.
+    fn list_object_pages<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Stream<Item = Result<Vec<Object>>> + 'a {
+        stream::try_unfold(Some(None::<String>), move |token_state| async move {
+            let Some(token) = token_state else {
+                return Ok(None);
+            };
 
-        loop {
             let mut request = self
                 .client
                 .list_objects_v2()
                 .bucket(&self.bucket)
-                .prefix(source_prefix);
+                .prefix(prefix);
 
-            if let Some(token) = &continuation_token {
+            if let Some(token) = &token {
                 request = request.continuation_token(token);
             }
 
             let response = request
                 .send()
                 .await
-                .context("Failed to list objects in source bucket")?;
-
-            if let Some(contents) = response.contents {
-                for object in contents {
-                    if let Some(key) = object.key {
-                        // Calculate new key by replacing source prefix with fork prefix
-                        let relative_path = key.strip_prefix(source_prefix).unwrap_or(&key);
-                        let new_key = format!("{}{}", fork_prefix, relative_path);
-
-                        // Copy object
-                        let copy_source = format!("{}/{}", self.bucket, key);
-                        self.client
-                            .copy_object()
-                            .bucket(&self.bucket)
-                            .key(&new_key)
-                            .copy_source(&copy_source)
-                            .send()
-                            .await
-                            .with_context(|| format!("Failed to copy object: {}", key))?;
-                    }
-                }
-            }
+                .context("Failed to list objects in bucket")?;
 
-            if response.is_truncated == Some(true) {
-                continuation_token = response.next_continuation_token;
-            } else {
-                break;
-            }
-        }
+            // Some S3-compatible servers report is_truncated = true without a
+            // continuation token to go with it; only continue when is_truncated
+            // is set AND a token came with it, so that case ends pagination
+            // instead of re-requesting the first page forever.
+            let next_token = response
+                .next_continuation_token
+                .filter(|_| response.is_truncated == Some(true))
+                .map(Some);
 
-        Ok(format!("s3://{}/{}", self.bucket, fork_prefix))
+            Ok(Some((response.contents.unwrap_or_default(), next_token)))
+        })
     }
 
-    /// Delete all objects under a fork prefix
-    pub async fn delete_fork(&self, fork_name: &str) -> Result<()> {
-        let fork_prefix = format!("{}{}/", self.prefix, fork_name);
-
-        // List and delete all objects under the fork prefix
-        let mut continuation_token: Option<String> = None;
-
-        loop {
-            let mut request = self
+    /// Copy a single object, transparently switching to a multipart server-side
+    /// copy for sources over `MULTIPART_THRESHOLD_BYTES`.
+    async fn copy_object(&self, key: &str, new_key: &str, size: Option<i64>) -> Result<()> {
+        let size = match size {
+            Some(size) => size,
+            None => self
                 .client
-                .list_objects_v2()
+                .head_object()
                 .bucket(&self.bucket)
-                .prefix(&fork_prefix);
+                .key(key)
+                .send()
+                .await
+                .with_context(|| format!("Failed to head object: {}", key))?
+                .content_length()
+                .unwrap_or(0),
+        };
 
-            if let Some(token) = &continuation_token {
-                request = request.continuation_token(token);
+        if size > MULTIPART_THRESHOLD_BYTES {
+            self.copy_object_multipart(key, new_key, size).await
+        } else {
+            let copy_source = encode_copy_source(&self.bucket, key);
+            self.client
+                .copy_object()
+                .bucket(&self.bucket)
+                .key(new_key)
+                .copy_source(&copy_source)
+                .send()
+                .await
+                .with_context(|| format!("Failed to copy object: {}", key))?;
+            Ok(())
+        }
+    }
+
+    /// Copy an object too large for a single `CopyObject` by issuing `upload_part_copy`
+    /// requests for successive byte ranges, aborting the upload on any failure so it
+    /// doesn't leak as an incomplete multipart upload.
+    async fn copy_object_multipart(&self, key: &str, new_key: &str, size: i64) -> Result<()> {
+        let upload_id = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(new_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to start multipart copy: {}", key))?
+            .upload_id
+            .with_context(|| format!("create_multipart_upload returned no upload id: {}", key))?;
+
+        match self
+            .copy_multipart_parts(key, new_key, &upload_id, size)
+            .await
+        {
+            Ok(parts) => {
+                self.client
+                    .complete_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(new_key)
+                    .upload_id(&upload_id)
+                    .multipart_upload(
+                        CompletedMultipartUpload::builder()
+                            .set_parts(Some(parts))
+                            .build(),
+                    )
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to complete multipart copy: {}", key))?;
+                Ok(())
             }
+            Err(err) => {
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(new_key)
+                    .upload_id(&upload_id)
+                    .send()
+                    .await;
+                Err(err)
+            }
+        }
+    }
 
-            let response = request
+    /// Issue the `upload_part_copy` calls for one multipart copy, in order, and
+    /// collect the ETags `complete_multipart_upload` needs.
+    async fn copy_multipart_parts(
+        &self,
+        key: &str,
+        new_key: &str,
+        upload_id: &str,
+        size: i64,
+    ) -> Result<Vec<CompletedPart>> {
+        let copy_source = encode_copy_source(&self.bucket, key);
+        // Respect the 10,000-part ceiling even for objects many times the default part size.
+        let part_size =
+            MULTIPART_PART_SIZE_BYTES.max((size + MAX_MULTIPART_PARTS - 1) / MAX_MULTIPART_PARTS);
+
+        let mut parts = Vec::new();
+        let mut start = 0i64;
+        let mut part_number = 1i32;
+
+        while start < size {
+            let end = (start + part_size - 1).min(size - 1);
+
+            let response = self
+                .client
+                .upload_part_copy()
+                .bucket(&self.bucket)
+                .key(new_key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .copy_source(&copy_source)
+                .copy_source_range(format!("bytes={}-{}", start, end))
                 .send()
                 .await
-                .context("Failed to list objects for deletion")?;
-
-            if let Some(contents) = response.contents {
-                for object in contents {
-                    if let Some(key) = object.key {
-                        self.client
-                            .delete_object()
-                            .bucket(&self.bucket)
-                            .key(&key)
-                            .send()
-                            .await
-                            .with_context(|| format!("Failed to delete object: {}", key))?;
-                    }
+                .with_context(|| format!("Failed to copy part {} of {}", part_number, key))?;
+
+            let e_tag = response
+                .copy_part_result
+                .and_then(|result| result.e_tag)
+                .with_context(|| format!("Missing ETag copying part {} of {}", part_number, key))?;
+
+            parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .e_tag(e_tag)
+                    .build(),
+            );
+
+            start = end + 1;
+            part_number += 1;
+        }
+
+        Ok(parts)
+    }
+
+    /// Copy all objects from source prefix to fork prefix, with up to
+    /// `self.concurrency` `copy_object` calls in flight at once (configurable via
+    /// `StorageConfig::concurrency`, defaulting to `DEFAULT_CONCURRENCY`). Objects
+    /// over 5 GiB are copied with a multipart server-side copy.
+    pub async fn copy_to_fork(&self, source_prefix: &str, fork_name: &str) -> Result<CopySummary> {
+        self.copy_to_fork_with_concurrency(source_prefix, fork_name, self.concurrency)
+            .await
+    }
+
+    /// Like [`Self::copy_to_fork`], but with an explicit bound on concurrent copies.
+    pub async fn copy_to_fork_with_concurrency(
+        &self,
+        source_prefix: &str,
+        fork_name: &str,
+        concurrency: usize,
+    ) -> Result<CopySummary> {
+        let fork_prefix = format!("{}{}/", self.prefix, fork_name);
+
+        let copied = self
+            .list_object_pages(source_prefix)
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+            .try_flatten()
+            .map(|object_result| {
+                let fork_prefix = &fork_prefix;
+                async move {
+                    let object = object_result?;
+                    let Some(key) = object.key else {
+                        return Ok(());
+                    };
+                    let size = object.size();
+
+                    let relative_path = key.strip_prefix(source_prefix).unwrap_or(&key);
+                    let new_key = format!("{}{}", fork_prefix, relative_path);
+
+                    self.copy_object(&key, &new_key, size).await
                 }
+            })
+            .buffer_unordered(concurrency)
+            // Fails fast on the first error: try_fold stops pulling from the
+            // buffered stream, which drops (and so cancels) the remaining
+            // in-flight copies.
+            .try_fold(0usize, |count, ()| async move { Ok(count + 1) })
+            .await?;
+
+        Ok(CopySummary {
+            url: format!("s3://{}/{}", self.bucket, fork_prefix),
+            copied,
+        })
+    }
+
+    /// Delete all objects under a fork prefix, with up to `self.concurrency`
+    /// `delete_object` calls in flight at once (configurable via
+    /// `StorageConfig::concurrency`, defaulting to `DEFAULT_CONCURRENCY`).
+    pub async fn delete_fork(&self, fork_name: &str) -> Result<usize> {
+        self.delete_fork_with_concurrency(fork_name, self.concurrency)
+            .await
+    }
+
+    /// Like [`Self::delete_fork`], but with an explicit bound on concurrent deletes.
+    pub async fn delete_fork_with_concurrency(
+        &self,
+        fork_name: &str,
+        concurrency: usize,
+    ) -> Result<usize> {
+        let fork_prefix = format!("{}{}/", self.prefix, fork_name);
+
+        let deleted = self
+            .list_object_pages(&fork_prefix)
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+            .try_flatten()
+            .map(|object_result| async move {
+                let object = object_result?;
+                let Some(key) = object.key else {
+                    return Ok(());
+                };
+
+                self.client
+                    .delete_object()
+                    .bucket(&self.bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to delete object: {}", key))?;
+
+                Ok(())
+            })
+            .buffer_unordered(concurrency)
+            .try_fold(0usize, |count, ()| async move { Ok(count + 1) })
+            .await?;
+
+        Ok(deleted)
+    }
+
+    /// Key of a fork's manifest object within this client's bucket/prefix.
+    fn manifest_key(&self, fork_name: &str) -> String {
+        format!("{}{}/.forkstack.json", self.prefix, fork_name)
+    }
+
+    /// Write a fork's manifest object into this bucket.
+    pub async fn write_manifest(&self, fork_name: &str, contents: &[u8]) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.manifest_key(fork_name))
+            .body(ByteStream::from(contents.to_vec()))
+            .send()
+            .await
+            .with_context(|| format!("Failed to write fork manifest for {}", fork_name))?;
+        Ok(())
+    }
+
+    /// Read a fork's manifest object, if one exists in this bucket.
+    pub async fn read_manifest(&self, fork_name: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.manifest_key(fork_name))
+            .send()
+            .await;
+
+        let output = match response {
+            Ok(output) => output,
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                return Ok(None)
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to fetch fork manifest for {}", fork_name))
             }
+        };
+
+        let bytes = output
+            .body
+            .collect()
+            .await
+            .with_context(|| format!("Failed to read fork manifest for {}", fork_name))?
+            .into_bytes();
+
+        Ok(Some(bytes.to_vec()))
+    }
+
+    /// Presign GET/PUT URLs for objects under a fork's storage prefix, so a
+    /// teammate or browser can read or write them without real credentials.
+    /// Presigns a single object when `object_key` is given, otherwise every
+    /// object currently under the fork's prefix.
+    pub async fn presign_fork(
+        &self,
+        fork_name: &str,
+        object_key: Option<&str>,
+        method: PresignMethod,
+        expires_in: Duration,
+    ) -> Result<Vec<PresignedUrl>> {
+        let fork_prefix = format!("{}{}/", self.prefix, fork_name);
 
-            if response.is_truncated == Some(true) {
-                continuation_token = response.next_continuation_token;
-            } else {
-                break;
+        let keys: Vec<String> = match object_key {
+            Some(suffix) => vec![format!("{}{}", fork_prefix, suffix)],
+            None => {
+                self.list_object_pages(&fork_prefix)
+                    .map_ok(|page| stream::iter(page.into_iter().filter_map(|o| o.key).map(Ok)))
+                    .try_flatten()
+                    .try_collect()
+                    .await?
             }
+        };
+
+        let mut urls = Vec::with_capacity(keys.len());
+        for key in keys {
+            let presigning_config = PresigningConfig::expires_in(expires_in)
+                .context("Invalid presign expiry")?;
+
+            let uri = match method {
+                PresignMethod::Get => {
+                    self.client
+                        .get_object()
+                        .bucket(&self.bucket)
+                        .key(&key)
+                        .presigned(presigning_config)
+                        .await
+                        .with_context(|| format!("Failed to presign GET for {}", key))?
+                        .uri()
+                        .to_string()
+                }
+                PresignMethod::Put => {
+                    self.client
+                        .put_object()
+                        .bucket(&self.bucket)
+                        .key(&key)
+                        .presigned(presigning_config)
+                        .await
+                        .with_context(|| format!("Failed to presign PUT for {}", key))?
+                        .uri()
+                        .to_string()
+                }
+            };
+
+            urls.push(PresignedUrl { key, url: uri });
         }
 
-        Ok(())
+        Ok(urls)
     }
 
     /// List fork prefixes in storage